@@ -1,11 +1,201 @@
 use actix_cors::Cors;
+use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
 use actix_web::{
-    middleware::Logger,
-    web, App, HttpResponse, HttpServer, Result as ActixResult,
+    cookie::Key,
+    http::header,
+    middleware::{Compress, Condition, DefaultHeaders},
+    web, App, HttpRequest, HttpResponse, HttpServer, Result as ActixResult,
 };
+use actix_ws::Message;
 use chrono::{DateTime, Utc};
+use clap::Parser;
+use futures_util::StreamExt as _;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tracing_actix_web::TracingLogger;
+
+/// How often we ping connected clients to check they're still alive.
+const WS_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// Default for how long a client can go without sending any frame before being dropped;
+/// overridable via `Cli::ws_idle_timeout_secs`.
+const DEFAULT_WS_CLIENT_TIMEOUT_SECS: u64 = 15;
+
+/// Per-connection settings for the `/ws` route, shared via `web::Data`.
+struct WsConfig {
+    /// Clients that go this long without sending any frame are considered dead and dropped.
+    idle_timeout: Duration,
+}
+
+/// Key used for the (currently single) coalesced stats computation.
+const STATS_KEY: &str = "stats";
+
+/// Custom response header stamped with the crate's version on every response.
+const X_VERSION_HEADER: &str = "X-Version";
+
+/// One in-flight computation shared by every request racing for the same key.
+struct Shared {
+    notify: Notify,
+    result: Mutex<Option<Result<StatsResponse, String>>>,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Self {
+            notify: Notify::new(),
+            result: Mutex::new(None),
+        }
+    }
+}
+
+/// Ensures a leader's entry is always cleared out of the in-flight map, even
+/// if `compute_stats` panics or returns early, so followers never hang forever.
+struct LeaderGuard<'a> {
+    cache: &'a StatsCache,
+    key: &'a str,
+    shared: Arc<Shared>,
+}
+
+impl Drop for LeaderGuard<'_> {
+    fn drop(&mut self) {
+        let mut result = self.shared.result.lock().unwrap();
+        if result.is_none() {
+            *result = Some(Err("leader task did not complete".to_string()));
+        }
+        drop(result);
+
+        self.shared.notify.notify_waiters();
+        self.cache.inflight.lock().unwrap().remove(self.key);
+    }
+}
+
+/// Shared cache backing the `/stats` endpoint: single-flights concurrent
+/// requests for the same key into one computation.
+struct StatsCache {
+    inflight: Mutex<HashMap<String, Arc<Shared>>>,
+    request_count: AtomicU64,
+}
+
+impl StatsCache {
+    fn new() -> Self {
+        Self {
+            inflight: Mutex::new(HashMap::new()),
+            request_count: AtomicU64::new(0),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct StatsResponse {
+    request_count: u64,
+    computed_at: DateTime<Utc>,
+}
+
+/// Simulates an expensive backend aggregation that we don't want a burst of
+/// concurrent requests to trigger more than once.
+async fn compute_stats(cache: &StatsCache) -> Result<StatsResponse, String> {
+    actix_web::rt::time::sleep(Duration::from_millis(250)).await;
+
+    let request_count = cache.request_count.fetch_add(1, Ordering::SeqCst) + 1;
+    Ok(StatsResponse {
+        request_count,
+        computed_at: Utc::now(),
+    })
+}
+
+// Stats endpoint - coalesces concurrent requests into a single computation
+async fn stats(cache: web::Data<StatsCache>) -> ActixResult<HttpResponse> {
+    let (shared, is_leader) = {
+        let mut inflight = cache.inflight.lock().unwrap();
+        if let Some(existing) = inflight.get(STATS_KEY) {
+            (existing.clone(), false)
+        } else {
+            let shared = Arc::new(Shared::new());
+            inflight.insert(STATS_KEY.to_string(), shared.clone());
+            (shared, true)
+        }
+    };
+
+    let result = if is_leader {
+        let guard = LeaderGuard {
+            cache: &cache,
+            key: STATS_KEY,
+            shared: shared.clone(),
+        };
+
+        let result = compute_stats(&cache).await;
+        *shared.result.lock().unwrap() = Some(result.clone());
+        drop(guard);
+
+        result
+    } else {
+        // `Notified` only enrolls as a waiter once it's polled (or `enable`d) -
+        // pin it and call `enable()` *before* checking for an existing result,
+        // so a `notify_waiters()` that lands in between isn't missed.
+        let notified = shared.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        // Bind the clone to a local so the lock guard drops here, before the
+        // `match` - otherwise the `None` arm would hold it across `.await`
+        // and deadlock the leader's write at line 131.
+        let cached = shared.result.lock().unwrap().clone();
+
+        match cached {
+            Some(result) => result,
+            None => {
+                notified.await;
+                let cached = shared.result.lock().unwrap().clone();
+                cached.unwrap_or_else(|| Err("leader task did not complete".to_string()))
+            }
+        }
+    };
+
+    match result {
+        Ok(stats) => Ok(HttpResponse::Ok().json(stats)),
+        Err(err) => {
+            tracing::warn!("Stats computation failed: {}", err);
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({ "error": err })))
+        }
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn concurrent_requests_coalesce_into_one_computation() {
+        let cache = web::Data::new(StatsCache::new());
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let cache = cache.clone();
+                actix_web::rt::spawn(async move { stats(cache).await })
+            })
+            .collect();
+
+        let mut counts = Vec::new();
+        for handle in handles {
+            let response = handle.await.unwrap().unwrap();
+            assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+
+            let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+            let parsed: StatsResponse = serde_json::from_slice(&body).unwrap();
+            counts.push(parsed.request_count);
+        }
+
+        // A burst of concurrent requests for the same key should be
+        // coalesced into exactly one computation: every response reports
+        // the same request_count, and the counter was only bumped once.
+        assert!(counts.iter().all(|&count| count == counts[0]));
+        assert_eq!(cache.request_count.load(Ordering::SeqCst), 1);
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 struct PingResponse {
@@ -28,12 +218,15 @@ struct RootResponse {
 
 // Root endpoint
 async fn root() -> ActixResult<HttpResponse> {
-    log::info!("Handled root request");
+    tracing::info!("Handled root request");
     
     let mut endpoints = HashMap::new();
     endpoints.insert("ping".to_string(), "/ping".to_string());
     endpoints.insert("health".to_string(), "/health".to_string());
-    
+    endpoints.insert("ws".to_string(), "/ws".to_string());
+    endpoints.insert("stats".to_string(), "/stats".to_string());
+    endpoints.insert("counter".to_string(), "/counter".to_string());
+
     let response = RootResponse {
         message: "Welcome to the Ping-Pong Server Rust Actix!".to_string(),
         endpoints,
@@ -44,7 +237,7 @@ async fn root() -> ActixResult<HttpResponse> {
 
 // Ping endpoint - optimized for minimal allocations
 async fn ping() -> ActixResult<HttpResponse> {
-    log::info!("Handled ping request");
+    tracing::info!("Handled ping request");
     
     let response = PingResponse {
         message: "pong".to_string(),
@@ -57,7 +250,7 @@ async fn ping() -> ActixResult<HttpResponse> {
 
 // Health endpoint
 async fn health() -> ActixResult<HttpResponse> {
-    log::info!("Handled health check request");
+    tracing::info!("Handled health check request");
     
     let response = HealthResponse {
         status: "healthy".to_string(),
@@ -67,9 +260,104 @@ async fn health() -> ActixResult<HttpResponse> {
     Ok(HttpResponse::Ok().json(response))
 }
 
+#[derive(Serialize)]
+struct CounterResponse {
+    count: i32,
+}
+
+// Counter endpoint - exercises session middleware (cookie parsing + deserialization)
+async fn counter(session: Session) -> ActixResult<HttpResponse> {
+    let count: i32 = session.get("counter")?.unwrap_or(0) + 1;
+    session.insert("counter", count)?;
+
+    tracing::info!("Handled counter request");
+    Ok(HttpResponse::Ok().json(CounterResponse { count }))
+}
+
+// WebSocket echo+ping endpoint
+async fn ws_index(
+    req: HttpRequest,
+    body: web::Payload,
+    config: web::Data<WsConfig>,
+) -> ActixResult<HttpResponse> {
+    let (response, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
+    let idle_timeout = config.idle_timeout;
+
+    actix_web::rt::spawn(async move {
+        let mut last_heartbeat = Instant::now();
+        let mut interval = actix_web::rt::time::interval(WS_HEARTBEAT_INTERVAL);
+
+        loop {
+            tokio::select! {
+                msg = msg_stream.next() => {
+                    let Some(Ok(msg)) = msg else {
+                        break;
+                    };
+
+                    match msg {
+                        Message::Text(text) => {
+                            last_heartbeat = Instant::now();
+
+                            if text.trim() == "ping" {
+                                let response = PingResponse {
+                                    message: "pong".to_string(),
+                                    timestamp: Utc::now(),
+                                    success: true,
+                                };
+
+                                let Ok(payload) = serde_json::to_string(&response) else {
+                                    break;
+                                };
+
+                                if session.text(payload).await.is_err() {
+                                    break;
+                                }
+                            } else if session.text(text).await.is_err() {
+                                break;
+                            }
+                        }
+                        Message::Binary(bytes) => {
+                            last_heartbeat = Instant::now();
+                            if session.binary(bytes).await.is_err() {
+                                break;
+                            }
+                        }
+                        Message::Ping(bytes) => {
+                            last_heartbeat = Instant::now();
+                            if session.pong(&bytes).await.is_err() {
+                                break;
+                            }
+                        }
+                        Message::Pong(_) => {
+                            last_heartbeat = Instant::now();
+                        }
+                        Message::Close(reason) => {
+                            let _ = session.close(reason).await;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                _ = interval.tick() => {
+                    if Instant::now().duration_since(last_heartbeat) > idle_timeout {
+                        tracing::warn!("Closing idle WebSocket client");
+                        let _ = session.close(None).await;
+                        break;
+                    }
+                    if session.ping(b"").await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(response)
+}
+
 // 404 handler
 async fn not_found() -> ActixResult<HttpResponse> {
-    log::warn!("Handled 404 request");
+    tracing::warn!("Handled 404 request");
     
     let response = PingResponse {
         message: "Not Found".to_string(),
@@ -80,40 +368,150 @@ async fn not_found() -> ActixResult<HttpResponse> {
     Ok(HttpResponse::NotFound().json(response))
 }
 
+/// Command-line configuration for the Ping-Pong benchmark server. Tuning
+/// worker count, bind address, and port without recompiling is central to
+/// running a fair Rust-vs-Python-vs-Node comparison.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Host/address to bind to.
+    #[arg(long, env = "HOST", default_value = "0.0.0.0")]
+    host: String,
+
+    /// Port to listen on.
+    #[arg(long, env = "PORT", default_value_t = 8000)]
+    port: u16,
+
+    /// Number of Actix worker threads.
+    #[arg(long, env = "WORKERS", default_value_t = 4)]
+    workers: usize,
+
+    /// Log filter passed to the tracing subscriber (e.g. "info", "debug,actix_web=warn").
+    /// Ignored when `RUST_LOG` is set to a valid filter.
+    #[arg(long, env = "LOG_LEVEL", default_value = "info")]
+    log_level: String,
+
+    /// Path to a TLS certificate (PEM). Enables HTTPS + HTTP/2 when set
+    /// together with `--tls-key`; plain HTTP/1.1 is used when unset.
+    #[arg(long, env = "TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+
+    /// Path to the TLS private key (PEM) matching `--tls-cert`.
+    #[arg(long, env = "TLS_KEY")]
+    tls_key: Option<PathBuf>,
+
+    /// How long a `/ws` client can go without sending any frame before being dropped, in seconds.
+    #[arg(long, env = "WS_IDLE_TIMEOUT_SECS", default_value_t = DEFAULT_WS_CLIENT_TIMEOUT_SECS)]
+    ws_idle_timeout_secs: u64,
+}
+
+/// Builds a rustls server config from a PEM certificate chain and private
+/// key, enabling the HTTPS + HTTP/2 listener.
+fn load_rustls_config(cert_path: &Path, key_path: &Path) -> std::io::Result<rustls::ServerConfig> {
+    let mut cert_reader = std::io::BufReader::new(std::fs::File::open(cert_path)?);
+    let mut key_reader = std::io::BufReader::new(std::fs::File::open(key_path)?);
+
+    let cert_chain = rustls_pemfile::certs(&mut cert_reader).collect::<Result<Vec<_>, _>>()?;
+    let mut keys = rustls_pemfile::pkcs8_private_keys(&mut key_reader).collect::<Result<Vec<_>, _>>()?;
+
+    let key = keys.pop().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "no PKCS#8 private key found in --tls-key file",
+        )
+    })?;
+
+    rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(cert_chain, rustls_pki_types::PrivateKeyDer::Pkcs8(key))
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Initialize logger
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
-    
-    let port = 8000;
-    let bind_address = format!("0.0.0.0:{}", port);
-    
+    let cli = Cli::parse();
+
+    // Initialize structured, span-based request tracing. Emits JSON log
+    // lines so request IDs, method, path, status, and latency can be
+    // aggregated when benchmarking (e.g. to compute p50/p99 latencies).
+    tracing_subscriber::fmt()
+        .json()
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(&cli.log_level)),
+        )
+        .init();
+
+    let bind_address = format!("{}:{}", cli.host, cli.port);
+    let scheme = if cli.tls_cert.is_some() && cli.tls_key.is_some() { "https" } else { "http" };
+
     println!("🚀 Ping-Pong server starting...");
-    println!("📡 Server will run on http://localhost:{}", port);
-    println!("🏓 Try: http://localhost:{}/ping", port);
+    println!("📡 Server will run on {}://{}", scheme, bind_address);
+    println!("🏓 Try: {}://localhost:{}/ping", scheme, cli.port);
     println!("🔧 Performance optimizations enabled!");
-    
-    HttpServer::new(|| {
+
+    let stats_cache = web::Data::new(StatsCache::new());
+    let ws_config = web::Data::new(WsConfig {
+        idle_timeout: Duration::from_secs(cli.ws_idle_timeout_secs),
+    });
+
+    // Compression is on by default; set DISABLE_COMPRESSION=1 to measure the
+    // server without the gzip/brotli negotiation overhead.
+    let compression_enabled = std::env::var("DISABLE_COMPRESSION").is_err();
+    let workers = cli.workers;
+
+    // Signs/encrypts the session cookie; generated once and shared across workers.
+    let session_key = Key::generate();
+
+    let server = HttpServer::new(move || {
         // Create CORS middleware
         let cors = Cors::default()
             .allow_any_origin()
             .allow_any_method()
             .allow_any_header()
             .max_age(3600);
-        
+
+        let security_headers = DefaultHeaders::new()
+            .add((X_VERSION_HEADER, env!("CARGO_PKG_VERSION")))
+            .add((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+            .add((header::X_FRAME_OPTIONS, "DENY"))
+            .add(("X-XSS-Protection", "1; mode=block"));
+
         App::new()
+            .app_data(stats_cache.clone())
+            .app_data(ws_config.clone())
             .wrap(cors)
-            .wrap(Logger::default())
+            .wrap(TracingLogger::default())
+            .wrap(security_headers)
+            .wrap(Condition::new(compression_enabled, Compress::default()))
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                session_key.clone(),
+            ))
             // Register routes
             .route("/", web::get().to(root))
             .route("/ping", web::get().to(ping))
             .route("/health", web::get().to(health))
+            .route("/ws", web::get().to(ws_index))
+            .route("/stats", web::get().to(stats))
+            .route("/counter", web::get().to(counter))
             // Default handler for 404
             .default_service(web::route().to(not_found))
     })
     // Performance optimizations
-    .workers(4) // Use 4 worker threads
-    .bind(&bind_address)?
-    .run()
-    .await
+    .workers(workers);
+
+    match (cli.tls_cert, cli.tls_key) {
+        (Some(cert_path), Some(key_path)) => {
+            let tls_config = load_rustls_config(&cert_path, &key_path)?;
+            println!("🔒 TLS enabled - serving HTTPS/HTTP2 on https://{}", bind_address);
+            server.bind_rustls_0_23(&bind_address, tls_config)?.run().await
+        }
+        (None, None) => server.bind(&bind_address)?.run().await,
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--tls-cert and --tls-key must be provided together",
+        )),
+    }
 }
\ No newline at end of file